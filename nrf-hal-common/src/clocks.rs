@@ -1,5 +1,7 @@
 //! Configuration and control of the High and Low Frequency Clock sources.
 
+use core::marker::PhantomData;
+
 #[cfg(feature = "9160")]
 use crate::pac::CLOCK_NS as CLOCK;
 
@@ -19,20 +21,124 @@ pub enum LfOscConfiguration {
     ExternalAndBypass,
 }
 
+/// High frequency clock source selection for [`ClockConfig`].
+pub enum HfclkSource {
+    /// Internal RC oscillator (HFINT). Works on any board, less accurate.
+    Internal,
+    /// External crystal oscillator (HFXO).
+    ExternalXtal,
+}
+
+/// Low frequency clock source selection for [`ClockConfig`].
+///
+/// Unlike [`LfOscConfiguration`], which only covers the external options,
+/// this covers every LF source and names the external setups by what is
+/// connected: a crystal wants neither bypass nor an external signal
+/// enabled, while driving LFCLK from an external signal source (as
+/// opposed to a crystal) needs `external` set and, for a full-swing signal,
+/// `bypass` set as well.
+pub enum LfclkSource {
+    /// Internal RC oscillator (LFRC).
+    InternalRC,
+    /// LFCLK synthesized from HFCLK.
+    Synthesized,
+    /// External crystal oscillator (LFXO).
+    ExternalXtal,
+    /// External low-swing signal source.
+    ExternalLowSwing,
+    /// External full-swing signal source.
+    ExternalFullSwing,
+}
+
+/// Configuration for [`Clocks::configure`].
+///
+/// The `Default` impl selects the internal oscillators for both clocks, so
+/// boards without any crystals fitted still come up running. `configure`
+/// currently only accepts this internal-only configuration; select an
+/// external oscillator via the type-state builder methods on `Clocks`
+/// instead.
+pub struct ClockConfig {
+    pub hfclk_source: HfclkSource,
+    pub lfclk_source: LfclkSource,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        ClockConfig {
+            hfclk_source: HfclkSource::Internal,
+            lfclk_source: LfclkSource::InternalRC,
+        }
+    }
+}
+
 /// A high level abstraction for the CLOCK peripheral.
-pub struct Clocks {
+///
+/// The `H`, `L` and `LSTAT` type parameters track, at compile time, which
+/// high frequency source, low frequency source and low frequency run state
+/// this `Clocks` value currently represents. Each configuration method
+/// consumes `self` and returns a `Clocks` with the type parameters updated
+/// to match, so that e.g. a driver requiring the external HFXO can demand a
+/// `Clocks<ExternalOscillator, L, LSTAT>` and have the compiler reject a
+/// `Clocks` that was never switched to it. The non-blocking
+/// [`Clocks::start_hfclk`] and [`Clocks::start_lfclk_nonblocking`] update
+/// the type parameters as soon as the start task is triggered, before the
+/// clock is actually running.
+pub struct Clocks<H, L, LSTAT> {
     periph: CLOCK,
+    _hfclk: PhantomData<H>,
+    _lfclk: PhantomData<L>,
+    _lfstat: PhantomData<LSTAT>,
+}
+
+/// Internally-driven clock source (HFINT or LFRC).
+pub struct Internal;
+
+/// Clock source driven by an external crystal oscillator.
+pub struct ExternalOscillator;
+
+/// Low frequency clock synthesized from the high frequency clock source.
+pub struct LfOscSynthesized;
+
+/// Marker type indicating the low frequency clock has been started. After
+/// [`Clocks::start_lfclk_nonblocking`] it may still be coming up.
+pub struct LfOscStarted;
+
+/// Marker type indicating the low frequency clock is currently stopped.
+pub struct LfOscStopped;
+
+/// Extension trait to constrain the raw `CLOCK` peripheral into the
+/// type-state `Clocks` abstraction.
+pub trait ClocksExt {
+    /// Take ownership of the `CLOCK` peripheral and return a `Clocks` in the
+    /// reset state: internal HFOSC, internal LFRC, LF clock stopped.
+    fn constrain(self) -> Clocks<Internal, Internal, LfOscStopped>;
+}
+
+impl ClocksExt for CLOCK {
+    fn constrain(self) -> Clocks<Internal, Internal, LfOscStopped> {
+        Clocks {
+            periph: self,
+            _hfclk: PhantomData,
+            _lfclk: PhantomData,
+            _lfstat: PhantomData,
+        }
+    }
 }
 
-impl Clocks {
-    pub fn new(clock: CLOCK) -> Clocks {
+impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
+    /// Returns the same peripheral wrapped in a `Clocks` with different type
+    /// parameters, without touching the peripheral.
+    fn retype<H2, L2, LSTAT2>(self) -> Clocks<H2, L2, LSTAT2> {
         Clocks {
-            periph: clock,
+            periph: self.periph,
+            _hfclk: PhantomData,
+            _lfclk: PhantomData,
+            _lfstat: PhantomData,
         }
     }
 
     /// Use an external oscillator as the high frequency clock source.
-    pub fn enable_ext_hfosc(&self) {
+    pub fn enable_ext_hfosc(self) -> Clocks<ExternalOscillator, L, LSTAT> {
         self.periph.tasks_hfclkstart.write(|w| unsafe { w.bits(1) });
 
         // Datasheet says this is likely to take 0.36ms
@@ -40,71 +146,198 @@ impl Clocks {
         self.periph
             .events_hfclkstarted
             .write(|w| unsafe { w.bits(0) });
+
+        self.retype()
     }
 
     /// Use the internal oscillator as the high frequency clock source.
-    pub fn disable_ext_hfosc(&self) {
+    pub fn disable_ext_hfosc(self) -> Clocks<Internal, L, LSTAT> {
         self.periph.tasks_hfclkstop.write(|w| unsafe { w.bits(1) });
+        self.retype()
     }
 
-    /// Start the Low Frequency clock.
-    pub fn start_lfclk(&self) {
-        self.periph.tasks_lfclkstart.write(|w| unsafe { w.bits(1) });
+    /// Start switching to the external oscillator as the high frequency
+    /// clock source, without waiting for it to come up.
+    ///
+    /// Use [`Clocks::is_hfclk_started`] to poll for completion, or
+    /// [`Clocks::enable_hfclkstarted_int`] to be notified via the
+    /// `HFCLKSTARTED` event/interrupt instead of busy-waiting.
+    pub fn start_hfclk(self) -> Clocks<ExternalOscillator, L, LSTAT> {
+        self.periph.tasks_hfclkstart.write(|w| unsafe { w.bits(1) });
+        self.retype()
+    }
 
-        // Datasheet says this could take 100us from synth source
-        // 600us from rc source, 0.25s from an external source.
-        while self.periph.events_lfclkstarted.read().bits() != 1 {}
+    /// Has the high frequency clock source finished starting?
+    pub fn is_hfclk_started(&self) -> bool {
+        self.periph.events_hfclkstarted.read().bits() != 0
+    }
+
+    /// Clear the `HFCLKSTARTED` event.
+    pub fn clear_hfclkstarted_event(&self) {
         self.periph
-            .events_lfclkstarted
+            .events_hfclkstarted
             .write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Enable the `HFCLKSTARTED` interrupt.
+    pub fn enable_hfclkstarted_int(&self) {
+        self.periph.intenset.write(|w| w.hfclkstarted().set());
+    }
 
+    /// Disable the `HFCLKSTARTED` interrupt.
+    pub fn disable_hfclkstarted_int(&self) {
+        self.periph.intenclr.write(|w| w.hfclkstarted().clear());
     }
 
     /// Stop the Low Frequency clock.
-    pub fn stop_lfclk(&self) {
+    pub fn stop_lfclk(self) -> Clocks<H, L, LfOscStopped> {
         self.periph.tasks_lfclkstop.write(|w| unsafe { w.bits(1) });
+        self.retype()
+    }
+
+    /// Has the low frequency clock source finished starting?
+    pub fn is_lfclk_started(&self) -> bool {
+        self.periph.events_lfclkstarted.read().bits() != 0
+    }
+
+    /// Clear the `LFCLKSTARTED` event.
+    pub fn clear_lfclkstarted_event(&self) {
+        self.periph
+            .events_lfclkstarted
+            .write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Enable the `LFCLKSTARTED` interrupt.
+    pub fn enable_lfclkstarted_int(&self) {
+        self.periph.intenset.write(|w| w.lfclkstarted().set());
+    }
+
+    /// Disable the `LFCLKSTARTED` interrupt.
+    pub fn disable_lfclkstarted_int(&self) {
+        self.periph.intenclr.write(|w| w.lfclkstarted().clear());
+    }
+
+    /// Is the high frequency clock currently running?
+    ///
+    /// Unlike the type parameters above, this reads `HFCLKSTAT` directly,
+    /// so it reflects reality even if something other than this `Clocks`
+    /// value (a reset, or the SoftDevice) changed the configuration.
+    pub fn hfclk_is_running(&self) -> bool {
+        self.periph.hfclkstat.read().state().is_running()
+    }
+
+    /// Which source is currently driving the high frequency clock, as
+    /// reported by `HFCLKSTAT`.
+    pub fn hfclk_source(&self) -> HfclkSource {
+        if self.periph.hfclkstat.read().src().is_xtal() {
+            HfclkSource::ExternalXtal
+        } else {
+            HfclkSource::Internal
+        }
+    }
+
+    /// Is the low frequency clock currently running?
+    pub fn lfclk_is_running(&self) -> bool {
+        self.periph.lfclkstat.read().state().is_running()
     }
 
+    /// Which source is currently driving the low frequency clock, as
+    /// reported by `LFCLKSTAT`.
+    ///
+    /// `LFCLKSTAT` only reports `RC`/`XTAL`/`SYNTH`, not the bypass/external
+    /// bits from `LFCLKSRC`, so `ExternalLowSwing` and `ExternalFullSwing`
+    /// both read back as `ExternalXtal`.
+    pub fn lfclk_source(&self) -> LfclkSource {
+        let stat = self.periph.lfclkstat.read();
+        if stat.src().is_synth() {
+            LfclkSource::Synthesized
+        } else if stat.src().is_xtal() {
+            LfclkSource::ExternalXtal
+        } else {
+            LfclkSource::InternalRC
+        }
+    }
+}
+
+impl Clocks<Internal, Internal, LfOscStopped> {
+    /// Construct a `Clocks` from a [`ClockConfig`], mirroring the
+    /// `Config`/`configure` entry points of other HALs.
+    ///
+    /// `Clocks`'s type parameters are a compile-time record of the actual
+    /// hardware state, but `cfg`'s sources are only known at runtime, so
+    /// there is no single `Self` this could honestly return for an
+    /// arbitrary `ClockConfig` — each HF/LF source combination would need a
+    /// different concrete `Clocks<...>` type depending on a value, not a
+    /// type. Rather than force them back down to a common type and have the
+    /// type parameters lie about what's actually running, `configure` only
+    /// supports the internal-oscillator `ClockConfig`
+    /// (`ClockConfig::default()`) and panics otherwise. It stops the HFXO
+    /// and the LF clock and (except on the 9160) selects the LFRC as the LF
+    /// source, so the returned `Clocks<Internal, Internal, LfOscStopped>`
+    /// matches the hardware. Use `enable_ext_hfosc` / `set_lfclk_src_external`
+    /// directly instead of `configure` to select an external oscillator —
+    /// those consume `self` and return a `Clocks` whose type parameters are
+    /// updated to match, so the type can never lie about the configuration.
+    pub fn configure(periph: CLOCK, cfg: ClockConfig) -> Self {
+        assert!(
+            matches!(cfg.hfclk_source, HfclkSource::Internal)
+                && matches!(cfg.lfclk_source, LfclkSource::InternalRC),
+            "Clocks::configure only supports the internal-oscillator ClockConfig; \
+             use the enable_ext_hfosc/set_lfclk_src_* builder methods for external sources"
+        );
+        let clocks = periph.constrain().disable_ext_hfosc().stop_lfclk();
+        #[cfg(not(feature = "9160"))]
+        let clocks = clocks.set_lfclk_src_rc();
+        clocks
+    }
+}
+
+impl<H, LSTAT> Clocks<H, Internal, LSTAT> {
     /// Use the internal RC Oscillator for the low frequency clock source.
     #[cfg(feature = "51")]
-    pub fn set_lfclk_src_rc(&self) {
+    pub fn set_lfclk_src_rc(self) -> Clocks<H, Internal, LSTAT> {
         self.periph.lfclksrc.write(|w| w.src().rc());
+        self.retype()
     }
 
     /// Generate the Low Frequency clock from the high frequency clock source.
     #[cfg(feature = "51")]
-    pub fn set_lfclk_src_synth(&self) {
+    pub fn set_lfclk_src_synth(self) -> Clocks<H, LfOscSynthesized, LSTAT> {
         self.periph.lfclksrc.write(|w| w.src().synth());
+        self.retype()
     }
 
     /// Use an external crystal to drive the low frequency clock.
     #[cfg(feature = "51")]
-    pub fn set_lfclk_src_external(&self) {
+    pub fn set_lfclk_src_external(self) -> Clocks<H, ExternalOscillator, LSTAT> {
         self.periph.lfclksrc.write(move |w| w.src().xtal());
+        self.retype()
     }
 
     /// Use the internal RC Oscillator for the low frequency clock source.
     #[cfg(not(any(feature = "9160", feature = "51")))]
-    pub fn set_lfclk_src_rc(&self) {
+    pub fn set_lfclk_src_rc(self) -> Clocks<H, Internal, LSTAT> {
         self.periph
             .lfclksrc
             .write(|w| w.src().rc().bypass().disabled().external().disabled());
+        self.retype()
     }
 
     /// Generate the Low Frequency clock from the high frequency clock source.
     #[cfg(not(any(feature = "9160", feature = "51")))]
-    pub fn set_lfclk_src_synth(&self) {
+    pub fn set_lfclk_src_synth(self) -> Clocks<H, LfOscSynthesized, LSTAT> {
         self.periph
             .lfclksrc
             .write(|w| w.src().synth().bypass().disabled().external().disabled());
+        self.retype()
     }
 
     /// Use an external crystal to drive the low frequency clock.
     #[cfg(not(any(feature = "9160", feature = "51")))]
     pub fn set_lfclk_src_external(
-        &self,
+        self,
         cfg: LfOscConfiguration,
-    ) {
+    ) -> Clocks<H, ExternalOscillator, LSTAT> {
         let (ext, byp) = match cfg {
             LfOscConfiguration::NoExternalNoBypass => (false, false),
             LfOscConfiguration::ExternalNoBypass => (true, false),
@@ -113,5 +346,125 @@ impl Clocks {
         self.periph
             .lfclksrc
             .write(move |w| w.src().xtal().bypass().bit(byp).external().bit(ext));
+        self.retype()
+    }
+}
+
+impl<H, L> Clocks<H, L, LfOscStopped> {
+    /// Start the Low Frequency clock.
+    pub fn start_lfclk(self) -> Clocks<H, L, LfOscStarted> {
+        self.periph.tasks_lfclkstart.write(|w| unsafe { w.bits(1) });
+
+        // Datasheet says this could take 100us from synth source
+        // 600us from rc source, 0.25s from an external source.
+        while self.periph.events_lfclkstarted.read().bits() != 1 {}
+        self.periph
+            .events_lfclkstarted
+            .write(|w| unsafe { w.bits(0) });
+
+        self.retype()
+    }
+
+    /// Start the Low Frequency clock, without waiting for it to come up.
+    ///
+    /// This avoids blocking for up to 0.25s on an external LF crystal; poll
+    /// [`Clocks::is_lfclk_started`] or react to the `LFCLKSTARTED`
+    /// event/interrupt (see [`Clocks::enable_lfclkstarted_int`]) instead.
+    pub fn start_lfclk_nonblocking(self) -> Clocks<H, L, LfOscStarted> {
+        self.periph.tasks_lfclkstart.write(|w| unsafe { w.bits(1) });
+        self.retype()
+    }
+}
+
+#[cfg(not(feature = "9160"))]
+impl<L, LSTAT> Clocks<ExternalOscillator, L, LSTAT> {
+    /// Trigger a calibration of the internal LFRC oscillator against the
+    /// HFCLK and block until it completes.
+    ///
+    /// This requires the external HFXO to actually be running. The
+    /// `ExternalOscillator` type parameter only proves that starting it was
+    /// requested at some point (see [`Clocks::start_hfclk`], which returns
+    /// before `HFCLKSTAT` confirms it), so this also asserts at runtime that
+    /// [`Clocks::hfclk_is_running`] holds and [`Clocks::hfclk_source`] is
+    /// the crystal.
+    pub fn calibrate(&self) {
+        assert!(
+            self.hfclk_is_running()
+                && matches!(self.hfclk_source(), HfclkSource::ExternalXtal),
+            "calibrate() requires the HFXO to be running"
+        );
+
+        self.periph
+            .events_done
+            .write(|w| unsafe { w.bits(0) });
+        self.periph.tasks_cal.write(|w| unsafe { w.bits(1) });
+        while self.periph.events_done.read().bits() != 1 {}
+        self.periph
+            .events_done
+            .write(|w| unsafe { w.bits(0) });
+    }
+}
+
+#[cfg(not(feature = "9160"))]
+impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
+    /// Start the calibration timer, firing `EVENTS_CTTO` once `interval`
+    /// (in units of 0.25s, `1..=127`) has elapsed. The timer then stops;
+    /// start it again after each `CTTO` to recalibrate the LFRC
+    /// periodically.
+    ///
+    /// The calibration timer runs off LFCLK and is independent of the HF
+    /// clock source, so unlike [`Clocks::calibrate`] this isn't restricted
+    /// to `Clocks<ExternalOscillator, _, _>`.
+    pub fn start_calibration_timer(&self, interval: u8) {
+        assert!((1..=127).contains(&interval));
+        self.periph
+            .ctiv
+            .write(|w| unsafe { w.ctiv().bits(interval) });
+        self.periph.tasks_ctstart.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Stop the calibration timer.
+    pub fn stop_calibration_timer(&self) {
+        self.periph.tasks_ctstop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Has the calibration task completed?
+    pub fn is_calibration_done(&self) -> bool {
+        self.periph.events_done.read().bits() != 0
+    }
+
+    /// Clear the `DONE` event.
+    pub fn clear_done_event(&self) {
+        self.periph.events_done.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Enable the `DONE` interrupt.
+    pub fn enable_done_int(&self) {
+        self.periph.intenset.write(|w| w.done().set());
+    }
+
+    /// Disable the `DONE` interrupt.
+    pub fn disable_done_int(&self) {
+        self.periph.intenclr.write(|w| w.done().clear());
+    }
+
+    /// Has the calibration timer timed out?
+    pub fn is_calibration_timeout(&self) -> bool {
+        self.periph.events_ctto.read().bits() != 0
+    }
+
+    /// Clear the `CTTO` event.
+    pub fn clear_ctto_event(&self) {
+        self.periph.events_ctto.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Enable the `CTTO` interrupt.
+    pub fn enable_ctto_int(&self) {
+        self.periph.intenset.write(|w| w.ctto().set());
+    }
+
+    /// Disable the `CTTO` interrupt.
+    pub fn disable_ctto_int(&self) {
+        self.periph.intenclr.write(|w| w.ctto().clear());
     }
 }